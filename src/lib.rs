@@ -1,32 +1,163 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use swc_core::common::DUMMY_SP;
-use swc_core::ecma::ast::{Ident, MemberExpr, MemberProp};
+use serde::Deserialize;
+use swc_core::common::errors::HANDLER;
+use swc_core::common::{Span, DUMMY_SP};
+use swc_core::ecma::ast::{
+    CallExpr, Callee, ExprOrSpread, Ident, KeyValueProp, MemberExpr, MemberProp, ObjectLit, Prop,
+    PropName, PropOrSpread, Str,
+};
 use swc_core::plugin::{plugin_transform, proxies::TransformPluginProgramMetadata};
 use swc_core::{
     ecma::{
-        ast::{Callee, Expr, Lit, Program},
+        ast::{Expr, Lit, Program},
         transforms::testing::test,
         visit::{as_folder, FoldWith, VisitMut, VisitMutWith},
     },
     plugin::metadata::TransformPluginMetadataContextKind,
 };
 
+fn default_callee_name() -> String {
+    "includeBytes".into()
+}
+
+/// Reports a diagnostic at `span` through SWC's error handler instead of
+/// panicking, so one bad `includeBytes`/`includeDir` call doesn't abort the
+/// whole compilation with an opaque wasm trap.
+fn emit_error(span: Span, message: &str) {
+    HANDLER.with(|handler| {
+        handler.struct_span_err(span, message).emit();
+    });
+}
+
+/// Per-project configuration, deserialized from the JSON blob SWC passes as
+/// the second element of the plugin tuple (e.g. `["include-bytes", { ... }]`
+/// in `.swcrc`).
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Config {
+    /// The identifier that triggers the transform. Defaults to
+    /// `includeBytes` so projects can rename it to avoid collisions.
+    #[serde(default = "default_callee_name")]
+    pub callee_name: String,
+    /// Overrides the `cwd` reported by SWC as the base directory for
+    /// cwd-relative includes.
+    #[serde(default)]
+    pub base_dir: Option<String>,
+    /// Maximum size, in bytes, of a file that may be embedded. `None` means
+    /// unlimited.
+    #[serde(default)]
+    pub max_file_size: Option<u64>,
+    /// When non-empty, only files with one of these extensions (without the
+    /// leading dot) may be embedded.
+    #[serde(default)]
+    pub extensions: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            callee_name: default_callee_name(),
+            base_dir: None,
+            max_file_size: None,
+            extensions: Vec::new(),
+        }
+    }
+}
+
 pub struct TransformVisitor {
-    is_include_bytes: bool,
     cwd: Option<String>,
-    #[allow(dead_code)]
     filename: Option<String>,
+    config: Config,
 }
 
 impl TransformVisitor {
-    pub fn new(filename: Option<String>, cwd: Option<String>) -> Self {
+    pub fn new(filename: Option<String>, cwd: Option<String>, config: Config) -> Self {
         Self {
-            is_include_bytes: false,
             filename,
             cwd,
+            config,
         }
     }
+
+    /// Resolves an `includeBytes`/`includeDir` argument the way ES module
+    /// imports resolve relative specifiers: against the directory of the
+    /// file containing the call, falling back to `cwd` when the filename
+    /// isn't known. Reports a diagnostic at `span` and returns `None` when
+    /// no base directory can be determined.
+    fn resolve_path(&self, label: &str, literal: &str, span: Span) -> Option<PathBuf> {
+        let literal_path = Path::new(literal);
+
+        if literal_path.is_absolute() {
+            return Some(literal_path.to_path_buf());
+        }
+
+        let base_dir = self
+            .filename
+            .as_ref()
+            .and_then(|filename| Path::new(filename).parent())
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .map(Path::to_path_buf)
+            .or_else(|| {
+                self.config
+                    .base_dir
+                    .as_ref()
+                    .map(|dir| Path::new(dir).to_path_buf())
+            })
+            .or_else(|| self.cwd.as_ref().map(|cwd| Path::new(cwd).to_path_buf()));
+
+        let Some(base_dir) = base_dir else {
+            emit_error(
+                span,
+                &format!("{label}: current working directory (cwd) is not set"),
+            );
+            return None;
+        };
+
+        Some(base_dir.join(literal_path))
+    }
+
+    /// Checks a candidate file against the configured extension allow-list
+    /// and `maxFileSize`, stat-ing it rather than reading it so an oversized
+    /// file is rejected before its contents are ever loaded into memory.
+    /// Emits a diagnostic at `span` and returns `false` on any violation.
+    fn check_file(&self, label: &str, path: &Path, span: Span) -> bool {
+        if !self.config.extensions.is_empty() {
+            let has_allowed_extension = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .map(|ext| self.config.extensions.iter().any(|allowed| allowed == ext))
+                .unwrap_or(false);
+
+            if !has_allowed_extension {
+                emit_error(
+                    span,
+                    &format!("{label}: file extension is not in the configured allow-list"),
+                );
+                return false;
+            }
+        }
+
+        if let Some(max_file_size) = self.config.max_file_size {
+            let Ok(metadata) = std::fs::metadata(path) else {
+                emit_error(
+                    span,
+                    &format!("{label}: failed to read file: {}", path.display()),
+                );
+                return false;
+            };
+
+            if metadata.len() > max_file_size {
+                emit_error(
+                    span,
+                    &format!("{label}: file exceeds the configured maxFileSize"),
+                );
+                return false;
+            }
+        }
+
+        true
+    }
 }
 
 impl VisitMut for TransformVisitor {
@@ -34,24 +165,161 @@ impl VisitMut for TransformVisitor {
     // A comprehensive list of possible visitor methods can be found here:
     // https://rustdoc.swc.rs/swc_ecma_visit/trait.VisitMut.html
 
-    fn visit_mut_callee(&mut self, callee: &mut Callee) {
-        if let Callee::Expr(expression) = callee {
-            if let Expr::Ident(ident) = &mut **expression {
-                if &*ident.sym == "includeBytes" {
-                    self.is_include_bytes = true;
-                }
+    fn visit_mut_expr(&mut self, n: &mut Expr) {
+        n.visit_mut_children_with(self);
+
+        let Expr::Call(call) = &n else {
+            return;
+        };
+
+        let Callee::Expr(callee) = &call.callee else {
+            return;
+        };
+
+        let Expr::Ident(ident) = &**callee else {
+            return;
+        };
+
+        if *ident.sym == *self.config.callee_name {
+            self.transform_include_bytes(n);
+        } else if &*ident.sym == "includeDir" {
+            self.transform_include_dir(n);
+        }
+    }
+}
+
+/// Builds `env.latin1_string_to_uint8array(<literal>)`, the call every
+/// embedded file is rewritten into.
+fn latin1_decode_call(literal: Str) -> Expr {
+    CallExpr {
+        span: DUMMY_SP,
+        callee: Callee::Expr(Box::new(
+            MemberExpr {
+                span: DUMMY_SP,
+                obj: Box::new(Ident::new("env".into(), DUMMY_SP).into()),
+                prop: MemberProp::Ident(
+                    Ident::new("latin1_string_to_uint8array".into(), DUMMY_SP).into(),
+                ),
+            }
+            .into(),
+        )),
+        args: vec![ExprOrSpread {
+            spread: None,
+            expr: Box::new(Expr::Lit(Lit::Str(literal))),
+        }],
+        type_args: None,
+    }
+    .into()
+}
+
+/// Maps each byte to its codepoint, the inverse of a Latin-1 decode, so the
+/// string round-trips every byte 0-255 through `env.latin1_string_to_uint8array`.
+fn bytes_to_latin1_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| *b as char).collect()
+}
+
+/// Recursively collects every regular file under `dir`, returning paths
+/// relative to `dir` with forward-slash separators (so generated keys are
+/// stable across platforms). Reports a diagnostic at `span` and returns
+/// `false` on the first filesystem error encountered.
+fn collect_files(
+    dir: &Path,
+    relative_to: &Path,
+    out: &mut Vec<(String, PathBuf)>,
+    span: Span,
+) -> bool {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        emit_error(span, "includeDir: failed to read directory");
+        return false;
+    };
+
+    for entry in entries {
+        let Ok(entry) = entry else {
+            emit_error(span, "includeDir: failed to read directory entry");
+            return false;
+        };
+
+        let Ok(file_type) = entry.file_type() else {
+            emit_error(span, "includeDir: failed to read directory entry");
+            return false;
+        };
+
+        let path = entry.path();
+
+        // Symlinked files are read like any other leaf file below. Only a
+        // symlinked *directory* is skipped, since following one that points
+        // back at an ancestor would send this recursion into an infinite
+        // loop.
+        if file_type.is_symlink() && path.is_dir() {
+            continue;
+        }
+
+        if file_type.is_dir() {
+            if !collect_files(&path, relative_to, out, span) {
+                return false;
             }
+            continue;
         }
+
+        let Ok(relative) = path.strip_prefix(relative_to) else {
+            emit_error(span, "includeDir: failed to compute relative path");
+            return false;
+        };
+
+        let key = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().into_owned())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        out.push((key, path));
     }
 
-    fn visit_mut_expr(&mut self, n: &mut Expr) {
-        n.visit_mut_children_with(self);
+    true
+}
+
+impl TransformVisitor {
+    fn transform_include_bytes(&mut self, n: &mut Expr) {
+        let Expr::Call(call) = n else {
+            return;
+        };
+        let span = call.span;
+        let label = self.config.callee_name.clone();
+
+        let Some(first) = call.args.first_mut() else {
+            emit_error(span, &format!("{label}: should have one argument"));
+            return;
+        };
+
+        let Expr::Lit(Lit::Str(string)) = &mut *first.expr else {
+            emit_error(
+                span,
+                &format!("{label}: should only have a string literal as an argument"),
+            );
+            return;
+        };
+
+        let Some(path) = self.resolve_path(&label, &string.value, string.span) else {
+            return;
+        };
+
+        if !path.exists() {
+            emit_error(
+                string.span,
+                &format!("{label}: file not found: {}", path.display()),
+            );
+            return;
+        }
 
-        if !self.is_include_bytes {
+        if !self.check_file(&label, &path, string.span) {
             return;
         }
 
-        let Expr::Call(call) = n else {
+        let Ok(bytes) = std::fs::read(&path) else {
+            emit_error(
+                string.span,
+                &format!("{label}: failed to read file: {}", path.display()),
+            );
             return;
         };
 
@@ -66,31 +334,70 @@ impl VisitMut for TransformVisitor {
             .into(),
         ));
 
-        let Some(first) = call.args.first_mut() else {
-            panic!("includeBytes(): should have one argument");
+        *string = bytes_to_latin1_string(&bytes).into();
+    }
+
+    fn transform_include_dir(&mut self, n: &mut Expr) {
+        let Expr::Call(call) = n else {
+            return;
         };
+        let span = call.span;
 
-        let Expr::Lit(Lit::Str(string)) = &mut *first.expr else {
-            panic!("includeBytes(): should only have a string literal as an argument");
+        let Some(first) = call.args.first() else {
+            emit_error(span, "includeDir: should have one argument");
+            return;
         };
 
-        let Some(cwd) = self.cwd.as_ref() else {
-            panic!("includeBytes(): current working directory (cwd) is not set");
+        let Expr::Lit(Lit::Str(string)) = &*first.expr else {
+            emit_error(
+                span,
+                "includeDir: should only have a string literal as an argument",
+            );
+            return;
         };
 
-        let path = Path::new(cwd).join(&*string.value);
+        let Some(dir) = self.resolve_path("includeDir", &string.value, string.span) else {
+            return;
+        };
 
-        if !path.exists() {
-            panic!("includeBytes(): file does not exist");
+        if !dir.is_dir() {
+            emit_error(
+                string.span,
+                &format!("includeDir: directory not found: {}", dir.display()),
+            );
+            return;
         }
 
-        let Ok(contents) = std::fs::read_to_string(path) else {
-            panic!("includeBytes(): failed to read file");
-        };
+        let mut files = Vec::new();
+        if !collect_files(&dir, &dir, &mut files, span) {
+            return;
+        }
+        files.sort_by(|(a, _), (b, _)| a.cmp(b));
 
-        *string = contents.into();
+        let mut props = Vec::with_capacity(files.len());
 
-        self.is_include_bytes = false;
+        for (key, path) in files {
+            if !self.check_file("includeDir", &path, span) {
+                return;
+            }
+
+            let Ok(bytes) = std::fs::read(&path) else {
+                emit_error(
+                    span,
+                    &format!("includeDir: failed to read file: {}", path.display()),
+                );
+                return;
+            };
+
+            let value = latin1_decode_call(bytes_to_latin1_string(&bytes).into());
+
+            props.push(PropOrSpread::Prop(Box::new(Prop::KeyValue(KeyValueProp {
+                key: PropName::Str(Str::from(key)),
+                value: Box::new(value),
+            }))));
+        }
+
+        *n = Expr::Object(ObjectLit { span, props });
     }
 }
 
@@ -114,7 +421,22 @@ pub fn process_transform(program: Program, metadata: TransformPluginProgramMetad
     let filename = metadata.get_context(&TransformPluginMetadataContextKind::Filename);
     let cwd = metadata.get_context(&TransformPluginMetadataContextKind::Cwd);
     println!("filename: {:?}", filename);
-    program.fold_with(&mut as_folder(TransformVisitor::new(filename, cwd)))
+
+    let config = match metadata.get_transform_plugin_config() {
+        Some(config) => match serde_json::from_str::<Config>(&config) {
+            Ok(config) => config,
+            Err(err) => {
+                emit_error(
+                    DUMMY_SP,
+                    &format!("include-bytes: invalid plugin config: {err}"),
+                );
+                return program;
+            }
+        },
+        None => Config::default(),
+    };
+
+    program.fold_with(&mut as_folder(TransformVisitor::new(filename, cwd, config)))
 }
 
 // An example to test plugin transform.
@@ -128,6 +450,7 @@ test!(
         std::env::current_dir()
             .ok()
             .map(|p| p.to_str().map(|s| s.to_string()).unwrap_or_default()),
+        Config::default(),
     )),
     boo,
     // Input codes
@@ -135,3 +458,237 @@ test!(
     // Output codes after transformed with plugin
     r#"const s = env.latin1_string_to_uint8array("/target\n^target/\ntarget\n");"#
 );
+
+test!(
+    Default::default(),
+    |_| as_folder(TransformVisitor::new(
+        Some("fixtures/somedir/fake.js".into()),
+        std::env::current_dir()
+            .ok()
+            .map(|p| p.to_str().map(|s| s.to_string()).unwrap_or_default()),
+        Config::default(),
+    )),
+    relative_to_filename,
+    // A bare relative specifier resolves against the directory of the
+    // importing file, not `cwd`, even though `sibling.txt` doesn't exist
+    // relative to the crate root.
+    r#"const s = includeBytes("sibling.txt");"#,
+    r#"const s = env.latin1_string_to_uint8array("abc");"#
+);
+
+test!(
+    Default::default(),
+    |_| as_folder(TransformVisitor::new(
+        None,
+        None,
+        Config {
+            base_dir: Some("fixtures/somedir".into()),
+            ..Default::default()
+        },
+    )),
+    base_dir_used_as_fallback_when_filename_is_absent,
+    // With no `filename` and no `cwd`, `baseDir` is the only thing that lets
+    // a bare relative specifier resolve at all.
+    r#"const s = includeBytes("sibling.txt");"#,
+    r#"const s = env.latin1_string_to_uint8array("abc");"#
+);
+
+test!(
+    Default::default(),
+    |_| as_folder(TransformVisitor::new(
+        None,
+        std::env::current_dir()
+            .ok()
+            .map(|p| p.to_str().map(|s| s.to_string()).unwrap_or_default()),
+        Config {
+            callee_name: "embed".into(),
+            ..Default::default()
+        },
+    )),
+    renamed_callee,
+    // `calleeName` lets projects trigger the transform on a different
+    // identifier than the default `includeBytes`.
+    r#"const s = embed(".gitignore");"#,
+    r#"const s = env.latin1_string_to_uint8array("/target\n^target/\ntarget\n");"#
+);
+
+test!(
+    Default::default(),
+    |_| as_folder(TransformVisitor::new(
+        None,
+        std::env::current_dir()
+            .ok()
+            .map(|p| p.to_str().map(|s| s.to_string()).unwrap_or_default()),
+        Config::default(),
+    )),
+    include_dir_produces_named_byte_arrays,
+    // `includeDir` walks the directory and rewrites the call into an object
+    // keyed by each file's path relative to that directory.
+    r#"const s = includeDir("fixtures/assets");"#,
+    r#"const s = {
+        "a.txt": env.latin1_string_to_uint8array("hi"),
+        "nested/b.txt": env.latin1_string_to_uint8array("yo"),
+    };"#
+);
+
+// `swc_ecma_transforms_testing::test!`'s tester fails a test the moment the
+// `Handler` records any error, regardless of whether the printed output
+// matches what's expected. That's the wrong tool for the diagnostic-path
+// tests below, where emitting an error *is* the behavior under test, so
+// they run through a small harness that captures diagnostics itself instead
+// of going through the `test!`/`Tester` machinery above.
+#[cfg(test)]
+mod diagnostics {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    use swc_core::common::errors::{DiagnosticBuilder, Emitter, Handler};
+
+    /// Collects every diagnostic message emitted while it's installed as the
+    /// active `Handler`'s emitter, instead of printing them.
+    #[derive(Clone, Default)]
+    struct DiagnosticSink(Arc<Mutex<Vec<String>>>);
+
+    impl Emitter for DiagnosticSink {
+        fn emit(&mut self, db: &DiagnosticBuilder<'_>) {
+            self.0.lock().unwrap().push(db.message());
+        }
+    }
+
+    /// Builds `<callee>(<literal>)` as a bare call expression, the same
+    /// shape every fixture test in this file exercises.
+    fn call_expr(callee: &str, literal: &str) -> Expr {
+        Expr::Call(CallExpr {
+            span: DUMMY_SP,
+            callee: Callee::Expr(Box::new(Ident::new(callee.into(), DUMMY_SP).into())),
+            args: vec![ExprOrSpread {
+                spread: None,
+                expr: Box::new(Expr::Lit(Lit::Str(literal.to_string().into()))),
+            }],
+            type_args: None,
+        })
+    }
+
+    /// Runs `visitor` over `expr` with a fresh `Handler`, returning the
+    /// (possibly rewritten) expression together with every diagnostic
+    /// message it emitted.
+    fn run(mut visitor: TransformVisitor, mut expr: Expr) -> (Expr, Vec<String>) {
+        let sink = DiagnosticSink::default();
+        let handler = Handler::with_emitter(true, false, Box::new(sink.clone()));
+
+        HANDLER.set(&handler, || expr.visit_mut_with(&mut visitor));
+
+        let messages = sink.0.lock().unwrap().clone();
+        (expr, messages)
+    }
+
+    fn default_cwd() -> Option<String> {
+        std::env::current_dir()
+            .ok()
+            .map(|p| p.to_str().map(|s| s.to_string()).unwrap_or_default())
+    }
+
+    #[test]
+    fn max_file_size_rejects_oversized_file() {
+        // A file larger than `maxFileSize` is left untouched rather than
+        // embedded.
+        let visitor = TransformVisitor::new(
+            None,
+            default_cwd(),
+            Config {
+                max_file_size: Some(1),
+                ..Default::default()
+            },
+        );
+
+        let (expr, diagnostics) = run(visitor, call_expr("includeBytes", ".gitignore"));
+
+        assert_eq!(
+            diagnostics,
+            vec!["includeBytes: file exceeds the configured maxFileSize".to_string()]
+        );
+        assert_eq!(expr, call_expr("includeBytes", ".gitignore"));
+    }
+
+    #[test]
+    fn extensions_reject_disallowed_file() {
+        // A file whose extension isn't in the allow-list is left untouched
+        // rather than embedded.
+        let visitor = TransformVisitor::new(
+            None,
+            default_cwd(),
+            Config {
+                extensions: vec!["txt".into()],
+                ..Default::default()
+            },
+        );
+
+        let (expr, diagnostics) = run(visitor, call_expr("includeBytes", ".gitignore"));
+
+        assert_eq!(
+            diagnostics,
+            vec!["includeBytes: file extension is not in the configured allow-list".to_string()]
+        );
+        assert_eq!(expr, call_expr("includeBytes", ".gitignore"));
+    }
+
+    #[test]
+    fn missing_file_reports_diagnostic_instead_of_panicking() {
+        // A nonexistent file reports a diagnostic at the call site and
+        // leaves the call untouched, rather than aborting the whole
+        // compilation.
+        let visitor = TransformVisitor::new(None, default_cwd(), Config::default());
+
+        let (expr, diagnostics) = run(visitor, call_expr("includeBytes", "does-not-exist.bin"));
+
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].starts_with("includeBytes: file not found:"));
+        assert_eq!(expr, call_expr("includeBytes", "does-not-exist.bin"));
+    }
+
+    #[test]
+    fn include_dir_rejects_oversized_file() {
+        // `maxFileSize` applies to every file `includeDir` walks, not just
+        // `includeBytes`; a directory containing a file over the limit is
+        // left untouched rather than partially embedded.
+        let visitor = TransformVisitor::new(
+            None,
+            default_cwd(),
+            Config {
+                max_file_size: Some(1),
+                ..Default::default()
+            },
+        );
+
+        let (expr, diagnostics) = run(visitor, call_expr("includeDir", "fixtures/assets"));
+
+        assert_eq!(
+            diagnostics,
+            vec!["includeDir: file exceeds the configured maxFileSize".to_string()]
+        );
+        assert_eq!(expr, call_expr("includeDir", "fixtures/assets"));
+    }
+
+    #[test]
+    fn include_dir_rejects_disallowed_extension() {
+        // Likewise, `extensions` applies to every file `includeDir` walks;
+        // none of `fixtures/assets`'s `.txt` files are in the allow-list
+        // here, so the call is left untouched.
+        let visitor = TransformVisitor::new(
+            None,
+            default_cwd(),
+            Config {
+                extensions: vec!["bin".into()],
+                ..Default::default()
+            },
+        );
+
+        let (expr, diagnostics) = run(visitor, call_expr("includeDir", "fixtures/assets"));
+
+        assert_eq!(
+            diagnostics,
+            vec!["includeDir: file extension is not in the configured allow-list".to_string()]
+        );
+        assert_eq!(expr, call_expr("includeDir", "fixtures/assets"));
+    }
+}